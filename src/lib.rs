@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use uuid::Uuid;
 use serde::{Serialize, Deserialize};
 
@@ -12,7 +13,17 @@ pub struct UpdateTodo {
     pub completed: Option<bool>,
 }
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
+pub struct ListOptions {
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+    pub completed: Option<bool>,
+    pub q: Option<String>,
+    pub sort: Option<String>,
+    pub order: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Id {
     pub id: Uuid
 }
@@ -22,6 +33,37 @@ pub struct Todo {
     pub id: Uuid,
     pub title: String,
     pub completed: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A mutation applied to the todo list, broadcast to live subscribers.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TodoEvent {
+    Added(Todo),
+    Updated(Todo),
+    Deleted(Id),
+    Cleared,
+}
+
+impl TodoEvent {
+    /// Name carried in the SSE `event:` field.
+    pub fn event_name(&self) -> &'static str {
+        match self {
+            TodoEvent::Added(_)   => "added",
+            TodoEvent::Updated(_) => "updated",
+            TodoEvent::Deleted(_) => "deleted",
+            TodoEvent::Cleared    => "cleared",
+        }
+    }
+
+    /// JSON payload carried in the SSE `data:` field.
+    pub fn payload(&self) -> String {
+        match self {
+            TodoEvent::Added(todo) | TodoEvent::Updated(todo) => serde_json::to_string(todo).unwrap(),
+            TodoEvent::Deleted(id) => serde_json::to_string(id).unwrap(),
+            TodoEvent::Cleared     => String::from("null"),
+        }
+    }
 }
 
 impl Todo {
@@ -30,15 +72,92 @@ impl Todo {
             id: Uuid::new_v4(),
             title: todo.title.clone(),
             completed: false,
+            created_at: Utc::now(),
         }
     }
 }
 
+/// Durable todo storage backed by a `sled` tree.
+///
+/// Each [`Todo`] is serialized with `serde_json` and stored under its [`Uuid`]
+/// key, so the collection survives process restarts.
+#[derive(Clone)]
+pub struct TodoStore {
+    tree: sled::Tree,
+}
+
+impl TodoStore {
+    /// Wraps an already opened sled tree.
+    pub fn new(tree: sled::Tree) -> Self {
+        TodoStore { tree }
+    }
+
+    /// Returns every stored todo, ordered by creation time.
+    ///
+    /// The sled tree is keyed by [`Uuid`], whose v4 bytes carry no ordering, so
+    /// we sort by `created_at` to give the default unsorted `GET /` a stable,
+    /// insertion-like order rather than an arbitrary key-byte order.
+    pub fn all(&self) -> Vec<Todo> {
+        let mut todos: Vec<Todo> = self
+            .tree
+            .iter()
+            .values()
+            .filter_map(Result::ok)
+            .filter_map(|bytes| serde_json::from_slice(&bytes).ok())
+            .collect();
+        todos.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+        todos
+    }
+
+    /// Lazily iterates over every stored todo without buffering the collection.
+    ///
+    /// Unlike [`all`](Self::all) this yields in sled key order and never
+    /// materializes the full set, so callers streaming large exports keep a
+    /// constant memory footprint.
+    pub fn iter(&self) -> impl Iterator<Item = Todo> {
+        self.tree
+            .iter()
+            .values()
+            .filter_map(Result::ok)
+            .filter_map(|bytes| serde_json::from_slice(&bytes).ok())
+    }
+
+    /// Looks up a single todo by its id.
+    pub fn get(&self, id: &Uuid) -> Option<Todo> {
+        self.tree
+            .get(id.as_bytes())
+            .ok()
+            .flatten()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+    }
+
+    /// Inserts or replaces a todo under its id.
+    pub fn insert(&self, todo: &Todo) {
+        let bytes = serde_json::to_vec(todo).unwrap();
+        let _ = self.tree.insert(todo.id.as_bytes(), bytes);
+    }
+
+    /// Removes a todo by id, returning whether it existed.
+    pub fn remove(&self, id: &Uuid) -> bool {
+        self.tree.remove(id.as_bytes()).ok().flatten().is_some()
+    }
+
+    /// Removes every stored todo.
+    pub fn clear(&self) {
+        let _ = self.tree.clear();
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
     use super::*;
 
+    fn store() -> TodoStore {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        TodoStore::new(db.open_tree("todos").unwrap())
+    }
+
     #[test]
     fn create_todo_from_new_todo() {
         let new = NewTodo { title: String::from("Learn Rust!") };
@@ -47,4 +166,27 @@ mod tests {
         assert_eq!(todo.completed, false);
         assert_eq!(todo.id.to_string().len(), 36);
     }
+
+    #[test]
+    fn insert_and_point_lookup() {
+        let store = store();
+        let todo = Todo::from_new(&NewTodo { title: String::from("test 1") });
+        store.insert(&todo);
+        assert_eq!(store.get(&todo.id), Some(todo));
+    }
+
+    #[test]
+    fn todos_survive_reopening_the_database() {
+        let dir = std::env::temp_dir().join(format!("todo-store-{}", Uuid::new_v4()));
+        let todo = Todo::from_new(&NewTodo { title: String::from("persist me") });
+        {
+            let db = sled::open(&dir).unwrap();
+            let store = TodoStore::new(db.open_tree("todos").unwrap());
+            store.insert(&todo);
+        }
+        let db = sled::open(&dir).unwrap();
+        let store = TodoStore::new(db.open_tree("todos").unwrap());
+        assert_eq!(store.get(&todo.id), Some(todo));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
\ No newline at end of file
@@ -1,83 +1,228 @@
 use std::io;
-use std::sync::{Mutex, Arc};
+use std::time::Duration;
 
-use actix_web::{web, middleware, App, HttpServer, HttpResponse};
+use actix_cors::Cors;
+use actix_web::{web, App, HttpServer, HttpRequest, HttpResponse};
+use futures::stream;
+use tokio::sync::broadcast;
+use tracing_actix_web::TracingLogger;
+use tracing_subscriber::EnvFilter;
 
 mod lib;
 
 use lib::*;
 
-#[derive(Debug, Default)]
-struct AppState {
-    items: Vec<Todo>,
+/// Builds a CORS middleware from environment variables.
+///
+/// `CORS_ALLOWED_ORIGINS`, `CORS_ALLOWED_METHODS` and `CORS_ALLOWED_HEADERS`
+/// are comma-separated lists and `CORS_MAX_AGE` is a number of seconds. When no
+/// origins are configured the policy is permissive in debug builds and
+/// restrictive (same-origin only) in release builds.
+fn cors_from_env() -> Cors {
+    let mut cors = Cors::new();
+    match std::env::var("CORS_ALLOWED_ORIGINS") {
+        Ok(origins) => {
+            for origin in origins.split(',').map(str::trim).filter(|o| !o.is_empty()) {
+                cors = cors.allowed_origin(origin);
+            }
+        },
+        Err(_) if cfg!(debug_assertions) => cors = cors.send_wildcard(),
+        Err(_) => {},
+    }
+    if let Ok(methods) = std::env::var("CORS_ALLOWED_METHODS") {
+        cors = cors.allowed_methods(methods.split(',').map(str::trim).collect::<Vec<_>>());
+    }
+    if let Ok(headers) = std::env::var("CORS_ALLOWED_HEADERS") {
+        cors = cors.allowed_headers(headers.split(',').map(str::trim).collect::<Vec<_>>());
+    }
+    if let Ok(Ok(max_age)) = std::env::var("CORS_MAX_AGE").map(|v| v.parse::<usize>()) {
+        cors = cors.max_age(max_age);
+    }
+    cors.finish()
 }
 
-/// Gets all active todo items.
-async fn get(data: web::Data<Arc<Mutex<AppState>>>) -> HttpResponse {
-    HttpResponse::Ok().json(&data.lock().unwrap().items) 
+/// Gets all active todo items, optionally filtered, sorted and paginated.
+async fn get(options: web::Query<ListOptions>, store: web::Data<TodoStore>) -> HttpResponse {
+    let mut items = store.all();
+    if let Some(completed) = options.completed {
+        items.retain(|todo| todo.completed == completed);
+    }
+    if let Some(query) = &options.q {
+        let needle = query.to_lowercase();
+        items.retain(|todo| todo.title.to_lowercase().contains(&needle));
+    }
+    match options.sort.as_deref() {
+        Some("title")   => items.sort_by(|a, b| a.title.cmp(&b.title)),
+        Some("created") => items.sort_by(|a, b| a.created_at.cmp(&b.created_at)),
+        _               => {},
+    }
+    if options.sort.is_some() && options.order.as_deref() == Some("desc") {
+        items.reverse();
+    }
+    let total = items.len();
+    let page: Vec<Todo> = items
+        .into_iter()
+        .skip(options.offset.unwrap_or(0))
+        .take(options.limit.unwrap_or(usize::MAX))
+        .collect();
+    HttpResponse::Ok()
+        .header("X-Total-Count", total.to_string())
+        .json(&page)
 }
 
 /// Deletes all active todo items.
-async fn delete(data: web::Data<Arc<Mutex<AppState>>>) -> HttpResponse {
-    data.lock().unwrap().items = Vec::default();
+async fn delete(store: web::Data<TodoStore>, tx: web::Data<broadcast::Sender<TodoEvent>>) -> HttpResponse {
+    store.clear();
+    let _ = tx.send(TodoEvent::Cleared);
     HttpResponse::Ok().finish()
 }
 
+/// Streams todo mutations to the client as Server-Sent Events.
+async fn events(tx: web::Data<broadcast::Sender<TodoEvent>>) -> HttpResponse {
+    let rx = tx.subscribe();
+    let keep_alive = tokio::time::interval(Duration::from_secs(15));
+    let stream = stream::unfold((rx, keep_alive), |(mut rx, mut keep_alive)| async move {
+        loop {
+            tokio::select! {
+                event = rx.recv() => match event {
+                    Ok(event) => {
+                        let frame = format!("event: {}\ndata: {}\n\n", event.event_name(), event.payload());
+                        return Some((Ok::<_, actix_web::Error>(web::Bytes::from(frame)), (rx, keep_alive)));
+                    }
+                    Err(broadcast::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::RecvError::Closed)    => return None,
+                },
+                _ = keep_alive.tick() => {
+                    return Some((Ok(web::Bytes::from_static(b": keep-alive\n\n")), (rx, keep_alive)));
+                }
+            }
+        }
+    });
+    HttpResponse::Ok()
+        .header("Content-Type", "text/event-stream")
+        .streaming(stream)
+}
+
 /// Gets active todo by its id.
-async fn get_todo(path: web::Path<Id>, data: web::Data<Arc<Mutex<AppState>>>) -> HttpResponse {
-    let todos = &data.lock().unwrap().items;
-    match todos.iter().find(|x| x.id == path.id) {
+async fn get_todo(path: web::Path<Id>, store: web::Data<TodoStore>) -> HttpResponse {
+    match store.get(&path.id) {
         Some(todo) => HttpResponse::Ok().json(&todo),
         None       => HttpResponse::NotFound().finish(),
     }
 }
 
 /// Adds new todo item.
-async fn add(todo: web::Json<NewTodo>, data: web::Data<Arc<Mutex<AppState>>>) -> HttpResponse {
+async fn add(todo: web::Json<NewTodo>, store: web::Data<TodoStore>, tx: web::Data<broadcast::Sender<TodoEvent>>) -> HttpResponse {
     let todo    = Todo::from_new(&todo.0);
     let todo_id = todo.id;
-    data.lock().unwrap().items.push(todo);
+    store.insert(&todo);
+    let _ = tx.send(TodoEvent::Added(todo));
     HttpResponse::Created().json(Id { id: todo_id })
 }
 
 /// Deletes active todo item by its id.
-async fn delete_todo(path: web::Path<Id>, data: web::Data<Arc<Mutex<AppState>>>) -> HttpResponse {
-    let index = data.lock().unwrap().items.iter().position(|x| x.id == path.id);
-    match index {
-        Some(index) => {
-            data.lock().unwrap().items.remove(index);
+async fn delete_todo(path: web::Path<Id>, store: web::Data<TodoStore>, tx: web::Data<broadcast::Sender<TodoEvent>>) -> HttpResponse {
+    if store.remove(&path.id) {
+        let _ = tx.send(TodoEvent::Deleted(Id { id: path.id }));
+        HttpResponse::Ok().finish()
+    } else {
+        HttpResponse::NotFound().finish()
+    }
+}
+
+/// Updates todo item identified by provided id.
+async fn update_todo(path: web::Path<Id>, update: web::Json<UpdateTodo>, store: web::Data<TodoStore>, tx: web::Data<broadcast::Sender<TodoEvent>>) -> HttpResponse {
+    match store.get(&path.id) {
+        Some(mut todo) => {
+            if let Some(t) = &update.title { todo.title = t.clone(); }
+            if let Some(c) = update.completed { todo.completed = c; }
+            store.insert(&todo);
+            let _ = tx.send(TodoEvent::Updated(todo));
             HttpResponse::Ok().finish()
         },
         None => HttpResponse::NotFound().finish(),
     }
 }
 
-/// Updates todo item identified by provided id.
-async fn update_todo(path: web::Path<Id>, update: web::Json<UpdateTodo>, data: web::Data<Arc<Mutex<AppState>>>) -> HttpResponse {
-    for item in &mut data.lock().unwrap().items {
-        if item.id == path.id {
-            if let Some(t) = &update.title { item.title = t.clone(); }
-            if let Some(c) = update.completed { item.completed = c; }
-            return HttpResponse::Ok().finish();
+/// Bulk-creates todos from a JSON array or NDJSON body, returning their ids.
+///
+/// The payload format is selected by `Content-Type`: an `ndjson` media type is
+/// parsed line by line, anything else as a JSON array. A malformed NDJSON line
+/// yields `400` naming the offending line number.
+async fn import(req: HttpRequest, body: web::Bytes, store: web::Data<TodoStore>, tx: web::Data<broadcast::Sender<TodoEvent>>) -> HttpResponse {
+    let content_type = req
+        .headers()
+        .get(actix_web::http::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("");
+    let new_todos: Vec<NewTodo> = if content_type.contains("ndjson") {
+        let mut parsed = Vec::new();
+        for (index, line) in String::from_utf8_lossy(&body).lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() { continue; }
+            match serde_json::from_str(line) {
+                Ok(todo) => parsed.push(todo),
+                Err(_)   => return HttpResponse::BadRequest().body(format!("invalid NDJSON on line {}", index + 1)),
+            }
         }
-    }
-    HttpResponse::NotFound().finish()
+        parsed
+    } else {
+        match serde_json::from_slice(&body) {
+            Ok(todos) => todos,
+            Err(_)    => return HttpResponse::BadRequest().body("invalid JSON array"),
+        }
+    };
+    let ids: Vec<Id> = new_todos
+        .iter()
+        .map(|new| {
+            let todo = Todo::from_new(new);
+            let id   = Id { id: todo.id };
+            store.insert(&todo);
+            let _ = tx.send(TodoEvent::Added(todo));
+            id
+        })
+        .collect();
+    HttpResponse::Created().json(ids)
+}
+
+/// Streams every stored todo as NDJSON, one JSON object per line.
+async fn export(store: web::Data<TodoStore>) -> HttpResponse {
+    let lines = store.iter().map(|todo| {
+        let mut line = serde_json::to_string(&todo).unwrap();
+        line.push('\n');
+        Ok::<_, actix_web::Error>(web::Bytes::from(line))
+    });
+    HttpResponse::Ok()
+        .header("Content-Type", "application/x-ndjson")
+        .streaming(stream::iter(lines))
 }
 
 #[actix_rt::main]
 async fn main() -> io::Result<()> {
-    env_logger::init();
-    let data = web::Data::new(Arc::new(Mutex::new(AppState::default())));
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .init();
+    let db_path = std::env::var("TODO_DB").unwrap_or_else(|_| String::from("./todo.db"));
+    let db = sled::open(db_path).expect("failed to open todo database");
+    let store = web::Data::new(TodoStore::new(db.open_tree("todos").expect("failed to open todos tree")));
+    let (tx, _rx) = broadcast::channel::<TodoEvent>(100);
+    let tx = web::Data::new(tx);
+    let bind_address = std::env::var("BIND_ADDRESS").unwrap_or_else(|_| String::from("127.0.0.1:9000"));
     HttpServer::new(move || {
         App::new()
-            .app_data(data.clone())
-            .wrap(middleware::Logger::default())
+            .app_data(store.clone())
+            .app_data(tx.clone())
+            .wrap(cors_from_env())
+            .wrap(TracingLogger)
             .service(
                 web::resource("/")
                     .route(web::get().to(get))
                     .route(web::post().to(add))
                     .route(web::delete().to(delete))
             )
+            .service(web::resource("/events").route(web::get().to(events)))
+            .service(web::resource("/import").route(web::post().to(import)))
+            .service(web::resource("/export").route(web::get().to(export)))
             .service(
                 web::resource("/{id}")
                     .route(web::get().to(get_todo))
@@ -85,7 +230,7 @@ async fn main() -> io::Result<()> {
                     .route(web::delete().to(delete_todo))
             )
     })
-    .bind("127.0.0.1:9000")?
+    .bind(bind_address)?
     .run()
     .await
 }
@@ -96,19 +241,31 @@ mod tests {
     use super::*;
     use actix_web::http;
 
+    /// Builds an in-memory store holding `todos`, wrapped as `web::Data`.
+    fn store(todos: Vec<Todo>) -> web::Data<TodoStore> {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let store = TodoStore::new(db.open_tree("todos").unwrap());
+        for todo in &todos { store.insert(todo); }
+        web::Data::new(store)
+    }
+
+    /// Builds a broadcast sender wrapped as `web::Data` for handlers that publish events.
+    fn events_tx() -> web::Data<broadcast::Sender<TodoEvent>> {
+        let (tx, _rx) = broadcast::channel::<TodoEvent>(16);
+        web::Data::new(tx)
+    }
+
     #[actix_rt::test]
     async fn get_status_code() {
-        let state    = AppState::default();
-        let data     = web::Data::new(Arc::new(Mutex::new(state)));
-        let response = get(data).await;
+        let data     = store(Vec::default());
+        let response = get(web::Query(ListOptions::default()), data).await;
         assert_eq!(response.status(), http::StatusCode::OK);
     }
 
     #[actix_rt::test]
     async fn get_empty_todos_json() {
-        let state    = AppState::default();
-        let data     = web::Data::new(Arc::new(Mutex::new(state)));
-        let response = get(data).await;
+        let data     = store(Vec::default());
+        let response = get(web::Query(ListOptions::default()), data).await;
         let body: Vec<Todo> = match response.body().as_ref() {
             Some(actix_web::body::Body::Bytes(bytes)) => serde_json::from_slice(bytes).unwrap(),
             _ => panic!("Response body error!")
@@ -117,42 +274,68 @@ mod tests {
     }
 
     #[actix_rt::test]
-    async fn get_todos_json() { 
-        let state = AppState { 
-            items: vec![
-                Todo::from_new(&NewTodo { title: String::from("test 1") }),
-                Todo::from_new(&NewTodo { title: String::from("test 2") }),
-                Todo::from_new(&NewTodo { title: String::from("test 3") }),
-            ] 
+    async fn get_todos_json() {
+        let data = store(vec![
+            Todo::from_new(&NewTodo { title: String::from("test 1") }),
+            Todo::from_new(&NewTodo { title: String::from("test 2") }),
+            Todo::from_new(&NewTodo { title: String::from("test 3") }),
+        ]);
+        let response = get(web::Query(ListOptions::default()), data.clone()).await;
+        assert_eq!(response.headers().get("X-Total-Count").unwrap(), "3");
+        let body: Vec<Todo> = match response.body().as_ref() {
+            Some(actix_web::body::Body::Bytes(bytes)) => serde_json::from_slice(bytes).unwrap(),
+            _ => panic!("Response body error!")
         };
-        let data = web::Data::new(Arc::new(Mutex::new(state)));
-        let response = get(data.clone()).await;
+        assert_eq!(body, data.all());
+    }
+
+    #[actix_rt::test]
+    async fn get_todos_offset_past_end() {
+        let data = store(vec![
+            Todo::from_new(&NewTodo { title: String::from("test 1") }),
+            Todo::from_new(&NewTodo { title: String::from("test 2") }),
+        ]);
+        let options = web::Query(ListOptions { offset: Some(10), limit: None, ..ListOptions::default() });
+        let response = get(options, data.clone()).await;
+        assert_eq!(response.headers().get("X-Total-Count").unwrap(), "2");
         let body: Vec<Todo> = match response.body().as_ref() {
             Some(actix_web::body::Body::Bytes(bytes)) => serde_json::from_slice(bytes).unwrap(),
             _ => panic!("Response body error!")
         };
-        assert_eq!(body, data.lock().unwrap().items);
+        assert_eq!(body, Vec::default());
     }
 
     #[actix_rt::test]
-    async fn delete_active_todo_items() {
-        let state = AppState {
-            items: vec![
-                Todo::from_new(&NewTodo { title: String::from("test 1") }),
-                Todo::from_new(&NewTodo { title: String::from("test 2") }),
-                Todo::from_new(&NewTodo { title: String::from("test 3") }),
-            ]
+    async fn get_todos_limit_zero() {
+        let data = store(vec![
+            Todo::from_new(&NewTodo { title: String::from("test 1") }),
+            Todo::from_new(&NewTodo { title: String::from("test 2") }),
+        ]);
+        let options = web::Query(ListOptions { offset: None, limit: Some(0), ..ListOptions::default() });
+        let response = get(options, data.clone()).await;
+        assert_eq!(response.headers().get("X-Total-Count").unwrap(), "2");
+        let body: Vec<Todo> = match response.body().as_ref() {
+            Some(actix_web::body::Body::Bytes(bytes)) => serde_json::from_slice(bytes).unwrap(),
+            _ => panic!("Response body error!")
         };
-        let data = web::Data::new(Arc::new(Mutex::new(state)));
-        let response = delete(data.clone()).await;
+        assert_eq!(body, Vec::default());
+    }
+
+    #[actix_rt::test]
+    async fn delete_active_todo_items() {
+        let data = store(vec![
+            Todo::from_new(&NewTodo { title: String::from("test 1") }),
+            Todo::from_new(&NewTodo { title: String::from("test 2") }),
+            Todo::from_new(&NewTodo { title: String::from("test 3") }),
+        ]);
+        let response = delete(data.clone(), events_tx()).await;
         assert_eq!(response.status(), http::StatusCode::OK);
-        assert_eq!(data.lock().unwrap().items, Vec::default());
+        assert_eq!(data.all(), Vec::default());
     }
 
     #[actix_rt::test]
     async fn get_todo_not_found_status() {
-        let state = AppState::default();
-        let data = web::Data::new(Arc::new(Mutex::new(state)));
+        let data = store(Vec::default());
         let path = web::Path::from(Id { id: uuid::Uuid::new_v4() });
         let response = get_todo(path, data).await;
         assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
@@ -160,141 +343,210 @@ mod tests {
 
     #[actix_rt::test]
     async fn get_todo_status() {
-        let state = AppState {
-            items: vec![
-                Todo::from_new(&NewTodo { title: String::from("test 1") }),
-            ]
-        };
-        let data = web::Data::new(Arc::new(Mutex::new(state)));
-        let path = web::Path::from(Id { id: data.lock().unwrap().items[0].id });
+        let todo = Todo::from_new(&NewTodo { title: String::from("test 1") });
+        let todo_id = todo.id;
+        let data = store(vec![todo]);
+        let path = web::Path::from(Id { id: todo_id });
         let response = get_todo(path, data.clone()).await;
         assert_eq!(response.status(), http::StatusCode::OK);
     }
 
     #[actix_rt::test]
     async fn get_todo_json() {
-        let state = AppState {
-            items: vec![
-                Todo::from_new(&NewTodo { title: String::from("test 1") }),
-            ]
-        };
-        let data = web::Data::new(Arc::new(Mutex::new(state)));
-        let path = web::Path::from(Id { id: data.lock().unwrap().items[0].id });
+        let todo = Todo::from_new(&NewTodo { title: String::from("test 1") });
+        let todo_id = todo.id;
+        let data = store(vec![todo]);
+        let path = web::Path::from(Id { id: todo_id });
         let response = get_todo(path, data.clone()).await;
         let body: Todo = match response.body().as_ref() {
             Some(actix_web::body::Body::Bytes(bytes)) => serde_json::from_slice(bytes).unwrap(),
             _ => panic!("Response body error!")
         };
-        assert_eq!(body, data.lock().unwrap().items[0]);
+        assert_eq!(body, data.get(&todo_id).unwrap());
     }
 
     #[actix_rt::test]
     async fn add_todo_status() {
-        let state = AppState::default();
-        let data = web::Data::new(Arc::new(Mutex::new(state)));
+        let data = store(Vec::default());
         let new = web::Json(NewTodo { title: String::from("Learn Rust") });
-        let response = add(new, data).await;
+        let response = add(new, data, events_tx()).await;
         assert_eq!(response.status(), http::StatusCode::CREATED);
-    } 
+    }
 
     #[actix_rt::test]
     async fn add_todo_json() {
-        let state = AppState::default();
-        let data = web::Data::new(Arc::new(Mutex::new(state)));
+        let data = store(Vec::default());
         let new = web::Json(NewTodo { title: String::from("Learn Rust") });
-        let response = add(new, data.clone()).await;
+        let response = add(new, data.clone(), events_tx()).await;
         let body: Id = match response.body().as_ref() {
             Some(actix_web::body::Body::Bytes(bytes)) => serde_json::from_slice(bytes).unwrap(),
             _ => panic!("Response body error!")
         };
-        let expected = Todo {
-            id: body.id,
-            title: String::from("Learn Rust"),
-            completed: false,
-        };
-        assert_eq!(body, Id { id: expected.id });
-        assert_eq!(expected, data.lock().unwrap().items[0]);
+        let stored = data.get(&body.id).unwrap();
+        assert_eq!(stored.title, String::from("Learn Rust"));
+        assert_eq!(stored.completed, false);
     }
 
     #[actix_rt::test]
     async fn delete_todo_not_found_status() {
-        let state = AppState::default();
-        let data = web::Data::new(Arc::new(Mutex::new(state)));
+        let data = store(Vec::default());
         let path = web::Path::from(Id { id: uuid::Uuid::new_v4() });
-        let response = delete_todo(path, data).await;
+        let response = delete_todo(path, data, events_tx()).await;
         assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
     }
 
     #[actix_rt::test]
     async fn delete_todo_status() {
-        let state = AppState {
-            items: vec![
-                Todo::from_new(&NewTodo { title: String::from("test 1") }),
-                Todo::from_new(&NewTodo { title: String::from("test 2") }),
-            ]
-        };
-        let data = web::Data::new(Arc::new(Mutex::new(state)));
-        let todo_id = data.lock().unwrap().items[0].id;
+        let first  = Todo::from_new(&NewTodo { title: String::from("test 1") });
+        let todo_id = first.id;
+        let data = store(vec![first, Todo::from_new(&NewTodo { title: String::from("test 2") })]);
         let path = web::Path::from(Id { id: todo_id });
-        let response = delete_todo(path, data.clone()).await;
+        let response = delete_todo(path, data.clone(), events_tx()).await;
         assert_eq!(response.status(), http::StatusCode::OK);
     }
 
     #[actix_rt::test]
     async fn delete_todo_updated_state() {
-        let state = AppState {
-            items: vec![
-                Todo::from_new(&NewTodo { title: String::from("test 1") }),
-                Todo::from_new(&NewTodo { title: String::from("test 2") }),
-            ]
-        };
-        let data = web::Data::new(Arc::new(Mutex::new(state)));
-        let todo_id = data.lock().unwrap().items[0].id;
+        let first  = Todo::from_new(&NewTodo { title: String::from("test 1") });
+        let todo_id = first.id;
+        let data = store(vec![first, Todo::from_new(&NewTodo { title: String::from("test 2") })]);
         let path = web::Path::from(Id { id: todo_id });
-        delete_todo(path, data.clone()).await;
-        assert_eq!(data.lock().unwrap().items.len(), 1);
-        assert_ne!(data.lock().unwrap().items[0].id, todo_id);
+        delete_todo(path, data.clone(), events_tx()).await;
+        assert_eq!(data.all().len(), 1);
+        assert_eq!(data.get(&todo_id), None);
     }
 
     #[actix_rt::test]
     async fn update_todo_not_found_status() {
-        let state = AppState::default();
-        let data = web::Data::new(Arc::new(Mutex::new(state)));
+        let data = store(Vec::default());
         let path = web::Path::from(Id { id: uuid::Uuid::new_v4() });
         let json = web::Json(UpdateTodo { title: None, completed: Some(true) });
-        let response = update_todo(path, json, data).await;
+        let response = update_todo(path, json, data, events_tx()).await;
         assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
     }
 
     #[actix_rt::test]
     async fn update_todo_updated_state() {
-        let state = AppState {
-            items: vec![
-                Todo::from_new(&NewTodo { title: String::from("test 1") }),
-                Todo::from_new(&NewTodo { title: String::from("test 2") }),
-            ]
-        };
-        let data = web::Data::new(Arc::new(Mutex::new(state)));
-        let todo_id = data.lock().unwrap().items[0].id;
+        let first  = Todo::from_new(&NewTodo { title: String::from("test 1") });
+        let todo_id = first.id;
+        let created_at = first.created_at;
+        let data = store(vec![first, Todo::from_new(&NewTodo { title: String::from("test 2") })]);
         let path = web::Path::from(Id { id: todo_id });
         let json = web::Json(UpdateTodo { title: Some(String::from("test 1 updated")), completed: Some(true) });
-        update_todo(path, json, data.clone()).await;
-        assert_eq!(data.lock().unwrap().items[0], Todo { id: todo_id, title: String::from("test 1 updated"), completed: true });
+        update_todo(path, json, data.clone(), events_tx()).await;
+        assert_eq!(data.get(&todo_id).unwrap(), Todo { id: todo_id, title: String::from("test 1 updated"), completed: true, created_at });
     }
 
     #[actix_rt::test]
     async fn update_todo_status() {
-        let state = AppState {
-            items: vec![
-                Todo::from_new(&NewTodo { title: String::from("test 1") }),
-                Todo::from_new(&NewTodo { title: String::from("test 2") }),
-            ]
-        };
-        let data = web::Data::new(Arc::new(Mutex::new(state)));
-        let todo_id = data.lock().unwrap().items[0].id;
+        let first  = Todo::from_new(&NewTodo { title: String::from("test 1") });
+        let todo_id = first.id;
+        let data = store(vec![first, Todo::from_new(&NewTodo { title: String::from("test 2") })]);
         let path = web::Path::from(Id { id: todo_id });
         let json = web::Json(UpdateTodo { title: Some(String::from("test 1 updated")), completed: Some(true) });
-        let response = update_todo(path, json, data.clone()).await;
+        let response = update_todo(path, json, data.clone(), events_tx()).await;
         assert_eq!(response.status(), http::StatusCode::OK);
     }
-}
\ No newline at end of file
+
+    #[actix_rt::test]
+    async fn get_todos_completed_filter() {
+        let mut done = Todo::from_new(&NewTodo { title: String::from("done") });
+        done.completed = true;
+        let data = store(vec![done, Todo::from_new(&NewTodo { title: String::from("pending") })]);
+        let options = web::Query(ListOptions { completed: Some(true), ..ListOptions::default() });
+        let response = get(options, data.clone()).await;
+        let body: Vec<Todo> = match response.body().as_ref() {
+            Some(actix_web::body::Body::Bytes(bytes)) => serde_json::from_slice(bytes).unwrap(),
+            _ => panic!("Response body error!")
+        };
+        assert_eq!(body.len(), 1);
+        assert_eq!(body[0].title, String::from("done"));
+    }
+
+    #[actix_rt::test]
+    async fn get_todos_substring_search() {
+        let data = store(vec![
+            Todo::from_new(&NewTodo { title: String::from("Buy MILK") }),
+            Todo::from_new(&NewTodo { title: String::from("Walk dog") }),
+        ]);
+        let options = web::Query(ListOptions { q: Some(String::from("milk")), ..ListOptions::default() });
+        let response = get(options, data.clone()).await;
+        let body: Vec<Todo> = match response.body().as_ref() {
+            Some(actix_web::body::Body::Bytes(bytes)) => serde_json::from_slice(bytes).unwrap(),
+            _ => panic!("Response body error!")
+        };
+        assert_eq!(body.len(), 1);
+        assert_eq!(body[0].title, String::from("Buy MILK"));
+    }
+
+    #[actix_rt::test]
+    async fn get_todos_sort_title_descending() {
+        let data = store(vec![
+            Todo::from_new(&NewTodo { title: String::from("apple") }),
+            Todo::from_new(&NewTodo { title: String::from("cherry") }),
+            Todo::from_new(&NewTodo { title: String::from("banana") }),
+        ]);
+        let options = web::Query(ListOptions {
+            sort: Some(String::from("title")),
+            order: Some(String::from("desc")),
+            ..ListOptions::default()
+        });
+        let response = get(options, data.clone()).await;
+        let body: Vec<Todo> = match response.body().as_ref() {
+            Some(actix_web::body::Body::Bytes(bytes)) => serde_json::from_slice(bytes).unwrap(),
+            _ => panic!("Response body error!")
+        };
+        let titles: Vec<String> = body.into_iter().map(|todo| todo.title).collect();
+        assert_eq!(titles, vec![String::from("cherry"), String::from("banana"), String::from("apple")]);
+    }
+
+    #[actix_rt::test]
+    async fn import_ndjson_status_and_ids() {
+        use actix_web::test::TestRequest;
+        let data = store(Vec::default());
+        let req = TestRequest::post().header("content-type", "application/x-ndjson").to_http_request();
+        let body = web::Bytes::from("{\"title\":\"test 1\"}\n{\"title\":\"test 2\"}\n");
+        let response = import(req, body, data.clone(), events_tx()).await;
+        assert_eq!(response.status(), http::StatusCode::CREATED);
+        let ids: Vec<Id> = match response.body().as_ref() {
+            Some(actix_web::body::Body::Bytes(bytes)) => serde_json::from_slice(bytes).unwrap(),
+            _ => panic!("Response body error!")
+        };
+        assert_eq!(ids.len(), 2);
+        assert_eq!(data.all().len(), 2);
+    }
+
+    #[actix_rt::test]
+    async fn import_ndjson_malformed_line() {
+        use actix_web::test::TestRequest;
+        let data = store(Vec::default());
+        let req = TestRequest::post().header("content-type", "application/x-ndjson").to_http_request();
+        let body = web::Bytes::from("{\"title\":\"test 1\"}\nnot json\n");
+        let response = import(req, body, data.clone(), events_tx()).await;
+        assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+        let message = match response.body().as_ref() {
+            Some(actix_web::body::Body::Bytes(bytes)) => String::from_utf8_lossy(bytes).into_owned(),
+            _ => panic!("Response body error!")
+        };
+        assert_eq!(message, "invalid NDJSON on line 2");
+        assert_eq!(data.all().len(), 0);
+    }
+
+    #[actix_rt::test]
+    async fn add_publishes_event() {
+        let data = store(Vec::default());
+        let (tx, mut rx) = broadcast::channel::<TodoEvent>(16);
+        let tx = web::Data::new(tx);
+        let new = web::Json(NewTodo { title: String::from("Learn Rust") });
+        let response = add(new, data.clone(), tx).await;
+        let body: Id = match response.body().as_ref() {
+            Some(actix_web::body::Body::Bytes(bytes)) => serde_json::from_slice(bytes).unwrap(),
+            _ => panic!("Response body error!")
+        };
+        let created = data.get(&body.id).unwrap();
+        match rx.recv().await.unwrap() {
+            TodoEvent::Added(todo) => assert_eq!(todo, created),
+            other => panic!("unexpected event: {:?}", other),
+        }
+    }
+}